@@ -0,0 +1,52 @@
+//! Generates `comp_to_bits` and `bits_to_comp` from `instructions.in` so the
+//! assembler and disassembler can never disagree about the comp encoding.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=instructions.in");
+
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let src = fs::read_to_string(Path::new(&manifest_dir).join("instructions.in")).unwrap();
+
+    let mut entries = Vec::new();
+    for line in src.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let mnemonic = parts.next().expect("line missing mnemonic");
+        let bits = parts.next().expect("line missing bit pattern");
+        assert_eq!(bits.len(), 7, "comp bit pattern must be 7 bits: {}", bits);
+        let value = u8::from_str_radix(bits, 2).expect("bit pattern must be binary");
+        entries.push((mnemonic.to_string(), value));
+    }
+
+    let mut out = String::new();
+    out.push_str("pub(crate) fn comp_to_bits(comp: &str) -> Option<u8> {\n    match comp {\n");
+    for (mnemonic, value) in &entries {
+        writeln!(out, "        {:?} => Some(0b{:07b}),", mnemonic, value).unwrap();
+    }
+    out.push_str("        _ => None,\n    }\n}\n\n");
+
+    out.push_str(
+        "pub(crate) fn bits_to_comp(bits: u8) -> Option<&'static str> {\n    match bits {\n",
+    );
+    for (mnemonic, value) in &entries {
+        writeln!(out, "        0b{:07b} => Some({:?}),", value, mnemonic).unwrap();
+    }
+    out.push_str("        _ => None,\n    }\n}\n\n");
+
+    out.push_str("#[cfg(test)]\npub(crate) const ALL_COMPS: &[&str] = &[\n");
+    for (mnemonic, _) in &entries {
+        writeln!(out, "    {:?},", mnemonic).unwrap();
+    }
+    out.push_str("];\n");
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("comp_table.rs"), out).unwrap();
+}
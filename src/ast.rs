@@ -0,0 +1,34 @@
+//! The structured representation the parser builds and codegen consumes.
+
+/// What an `@` instruction addresses. `Value` carries the raw digit text
+/// rather than a parsed number — it may overflow `usize`, which codegen
+/// reports as a diagnostic rather than the parser panicking on it.
+#[derive(Debug, Clone)]
+pub(crate) enum ATarget {
+    Value(String),
+    Symbol(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Instruction {
+    A {
+        target: ATarget,
+    },
+    Label {
+        name: String,
+    },
+    C {
+        dest: Option<String>,
+        comp: String,
+        jump: Option<String>,
+    },
+}
+
+/// An AST node tagged with the source line/column it was parsed from, for
+/// diagnostics.
+#[derive(Debug, Clone)]
+pub(crate) struct SpannedInstruction {
+    pub(crate) instruction: Instruction,
+    pub(crate) line: usize,
+    pub(crate) col: usize,
+}
@@ -0,0 +1,120 @@
+//! Two-pass codegen over the AST: the first walk assigns every `(LABEL)`
+//! its ROM address (a label doesn't emit a word, so its address is just the
+//! count of instructions seen so far); the second walk emits the actual
+//! words, now that every label's address is known regardless of whether
+//! it's defined before or after its use.
+
+use std::collections::HashMap;
+
+use crate::ast::{ATarget, Instruction, SpannedInstruction};
+use crate::{comp_to_bits, dest_to_bits, jump_to_bits, AssembleError, AssembleErrorKind};
+
+pub(crate) enum PartiallyAssembledInstruction {
+    Complete(u16),
+    SymbolicAddress(String),
+}
+
+pub(crate) fn generate(
+    instructions: &[SpannedInstruction],
+    file: &str,
+) -> (
+    Vec<PartiallyAssembledInstruction>,
+    HashMap<String, u16>,
+    Vec<AssembleError>,
+) {
+    let mut labels = HashMap::new();
+    let mut rom_address: u16 = 0;
+    for instruction in instructions {
+        match &instruction.instruction {
+            Instruction::Label { name } => {
+                labels.insert(name.clone(), rom_address);
+            }
+            _ => rom_address += 1,
+        }
+    }
+
+    let mut output = Vec::new();
+    let mut errors = Vec::new();
+    for instruction in instructions {
+        let line = instruction.line;
+        let col = instruction.col;
+        match &instruction.instruction {
+            Instruction::Label { .. } => {}
+            Instruction::A { target } => match target {
+                ATarget::Value(text) => match text.parse::<usize>() {
+                    Ok(value) if value > 0b01111111_11111111 => {
+                        errors.push(AssembleError {
+                            file: file.to_string(),
+                            line,
+                            col,
+                            kind: AssembleErrorKind::AValueTooLarge(value),
+                        });
+                    }
+                    Ok(value) => {
+                        output.push(PartiallyAssembledInstruction::Complete(value as u16));
+                    }
+                    Err(_) => {
+                        errors.push(AssembleError {
+                            file: file.to_string(),
+                            line,
+                            col,
+                            kind: AssembleErrorKind::InvalidNumber(text.clone()),
+                        });
+                    }
+                },
+                ATarget::Symbol(name) => {
+                    output.push(PartiallyAssembledInstruction::SymbolicAddress(name.clone()));
+                }
+            },
+            Instruction::C { dest, comp, jump } => {
+                // Validate dest/comp/jump independently so a line with more
+                // than one malformed field (e.g. `BADCOMP;BADJUMP`) reports
+                // every error in it, not just the first one encountered.
+                let dest_bits = match dest {
+                    Some(name) => dest_to_bits(name),
+                    None => Some(0),
+                };
+                if dest_bits.is_none() {
+                    errors.push(AssembleError {
+                        file: file.to_string(),
+                        line,
+                        col,
+                        kind: AssembleErrorKind::InvalidDest(dest.clone().unwrap()),
+                    });
+                }
+                let jump_bits = match jump {
+                    Some(name) => jump_to_bits(name),
+                    None => Some(0),
+                };
+                if jump_bits.is_none() {
+                    errors.push(AssembleError {
+                        file: file.to_string(),
+                        line,
+                        col,
+                        kind: AssembleErrorKind::InvalidJump(jump.clone().unwrap()),
+                    });
+                }
+                let comp_bits = comp_to_bits(comp);
+                if comp_bits.is_none() {
+                    errors.push(AssembleError {
+                        file: file.to_string(),
+                        line,
+                        col,
+                        kind: AssembleErrorKind::InvalidComp(comp.clone()),
+                    });
+                }
+                if let (Some(dest_bits), Some(jump_bits), Some(comp_bits)) =
+                    (dest_bits, jump_bits, comp_bits)
+                {
+                    let full = 0b111_00000_00000000
+                        | (comp_bits as u16) << 6
+                        | (dest_bits as u16) << 3
+                        | jump_bits as u16;
+                    output.push(PartiallyAssembledInstruction::Complete(full));
+                }
+            }
+        }
+    }
+
+    (output, labels, errors)
+}
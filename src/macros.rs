@@ -0,0 +1,192 @@
+//! `.macro NAME arg0 arg1 ... / ... / .end` pre-pass: pulls macro
+//! definitions out of the raw source and expands every invocation,
+//! recursively, before the tokeniser ever sees the result.
+
+use std::collections::HashMap;
+
+use crate::{AssembleError, AssembleErrorKind};
+
+const MAX_MACRO_EXPANSION_DEPTH: usize = 64;
+
+/// A macro's body lines are expanded textually at each call site, with
+/// `%param%` replaced by the corresponding positional argument.
+#[derive(Clone)]
+struct MacroDef {
+    params: Vec<String>,
+    body: Vec<String>,
+}
+
+/// Labels `(NAME)` defined inside a macro body, so each expansion can gensym
+/// them and avoid colliding with a sibling expansion's symbol_table entries.
+fn local_labels(body: &[String]) -> Vec<String> {
+    body.iter()
+        .filter_map(|line| {
+            let trimmed = line.trim();
+            if trimmed.starts_with('(') && trimmed.ends_with(')') {
+                Some(trimmed[1..trimmed.len() - 1].to_string())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+pub(crate) struct MacroExpander {
+    file: String,
+    macros: HashMap<String, MacroDef>,
+    macro_expansion_counter: u64,
+    errors: Vec<AssembleError>,
+}
+
+impl MacroExpander {
+    pub(crate) fn new(file: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            macros: HashMap::new(),
+            macro_expansion_counter: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    fn error(&self, line: usize, col: usize, kind: AssembleErrorKind) -> AssembleError {
+        AssembleError {
+            file: self.file.clone(),
+            line,
+            col,
+            kind,
+        }
+    }
+
+    /// Expands `source`, returning the flattened text alongside a
+    /// `line_map`: `line_map[i]` is the original source line that produced
+    /// flattened line `i + 1`. Expansion changes the line count (macro
+    /// definitions are deleted, invocations become N body lines), so the
+    /// tokeniser can't recover the real line from the flattened text alone.
+    pub(crate) fn expand(mut self, source: &str) -> (String, Vec<usize>, Vec<AssembleError>) {
+        let mut lines = source.lines();
+        let mut plain_lines = Vec::new();
+        let mut line_no = 0;
+        while let Some(line) = lines.next() {
+            line_no += 1;
+            let trimmed = line.trim();
+            if let Some(rest) = trimmed.strip_prefix(".macro") {
+                let mut parts = rest.split_whitespace();
+                let name = match parts.next() {
+                    Some(name) => name.to_string(),
+                    None => {
+                        self.errors.push(self.error(
+                            line_no,
+                            1,
+                            AssembleErrorKind::MalformedMacro(String::from(
+                                ".macro requires a name",
+                            )),
+                        ));
+                        continue;
+                    }
+                };
+                let params: Vec<String> = parts.map(String::from).collect();
+                let mut body = Vec::new();
+                loop {
+                    match lines.next() {
+                        Some(body_line) => {
+                            line_no += 1;
+                            if body_line.trim() == ".end" {
+                                break;
+                            }
+                            body.push(body_line.to_string());
+                        }
+                        None => {
+                            self.errors.push(self.error(
+                                line_no,
+                                1,
+                                AssembleErrorKind::MalformedMacro(format!(
+                                    "macro {} is missing a closing .end",
+                                    name
+                                )),
+                            ));
+                            break;
+                        }
+                    }
+                }
+                self.macros.insert(name, MacroDef { params, body });
+            } else {
+                plain_lines.push((line_no, line.to_string()));
+            }
+        }
+        let mut expanded = String::new();
+        let mut line_map = Vec::new();
+        for (line_no, line) in &plain_lines {
+            self.expand_line(line, *line_no, &mut expanded, &mut line_map, 0);
+        }
+        (expanded, line_map, self.errors)
+    }
+
+    fn expand_line(
+        &mut self,
+        line: &str,
+        line_no: usize,
+        out: &mut String,
+        line_map: &mut Vec<usize>,
+        depth: usize,
+    ) {
+        let trimmed = line.trim();
+        let first = match trimmed.split_whitespace().next() {
+            Some(first) => first,
+            None => {
+                out.push_str(line);
+                out.push('\n');
+                line_map.push(line_no);
+                return;
+            }
+        };
+        let def = match self.macros.get(first) {
+            Some(def) => def.clone(),
+            None => {
+                out.push_str(line);
+                out.push('\n');
+                line_map.push(line_no);
+                return;
+            }
+        };
+        if depth >= MAX_MACRO_EXPANSION_DEPTH {
+            self.errors.push(self.error(
+                line_no,
+                1,
+                AssembleErrorKind::MacroRecursionTooDeep(first.to_string()),
+            ));
+            return;
+        }
+        let args: Vec<&str> = trimmed.split_whitespace().skip(1).collect();
+        if args.len() != def.params.len() {
+            self.errors.push(self.error(
+                line_no,
+                1,
+                AssembleErrorKind::MalformedMacro(format!(
+                    "macro {} expects {} argument(s), got {}",
+                    first,
+                    def.params.len(),
+                    args.len()
+                )),
+            ));
+            return;
+        }
+        let suffix = self.macro_expansion_counter;
+        self.macro_expansion_counter += 1;
+        let local_labels = local_labels(&def.body);
+        for body_line in &def.body {
+            let mut substituted = body_line.clone();
+            for (i, param) in def.params.iter().enumerate() {
+                if let Some(arg) = args.get(i) {
+                    substituted = substituted.replace(&format!("%{}%", param), arg);
+                }
+            }
+            for label in &local_labels {
+                substituted = substituted
+                    .replace(&format!("({})", label), &format!("({}${})", label, suffix));
+                substituted =
+                    substituted.replace(&format!("@{}", label), &format!("@{}${}", label, suffix));
+            }
+            self.expand_line(&substituted, line_no, out, line_map, depth + 1);
+        }
+    }
+}
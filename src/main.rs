@@ -1,15 +1,123 @@
 use std::collections::HashMap;
 
-#[derive(Clone, Copy)]
-enum AssemblerState {
-    LookingForInstruction,
-    OneSlash,
-    Comment,
+mod ast;
+mod codegen;
+mod macros;
+mod parser;
+mod token;
+mod tokeniser;
+
+use codegen::PartiallyAssembledInstruction;
+use macros::MacroExpander;
+
+// Generated by build.rs from `instructions.in`: `comp_to_bits`/`bits_to_comp`.
+// Keeping these in one generated module is what guarantees the assembler
+// and disassembler can never drift apart on the comp encoding.
+include!(concat!(env!("OUT_DIR"), "/comp_table.rs"));
+
+/// `None` if `dest_name` contains anything other than `A`/`M`/`D`, or repeats
+/// one of them — e.g. `X` and `AA` are not valid dest mnemonics.
+pub(crate) fn dest_to_bits(dest_name: &str) -> Option<u8> {
+    let mut dest = 0;
+    for c in dest_name.chars() {
+        let bit = match c {
+            'A' => 0b100,
+            'M' => 0b1,
+            'D' => 0b10,
+            _ => return None,
+        };
+        if dest & bit != 0 {
+            return None;
+        }
+        dest |= bit;
+    }
+    Some(dest)
+}
+
+fn bits_to_dest(bits: u8) -> String {
+    let mut dest = String::new();
+    if bits & 0b100 != 0 {
+        dest.push('A');
+    }
+    if bits & 0b1 != 0 {
+        dest.push('M');
+    }
+    if bits & 0b10 != 0 {
+        dest.push('D');
+    }
+    dest
+}
+
+pub(crate) fn jump_to_bits(jump_name: &str) -> Option<u8> {
+    Some(match jump_name {
+        "null" => 0b000,
+        "JGT" => 0b001,
+        "JEQ" => 0b010,
+        "JGE" => 0b011,
+        "JLT" => 0b100,
+        "JNE" => 0b101,
+        "JLE" => 0b110,
+        "JMP" => 0b111,
+        _ => return None,
+    })
+}
+
+fn bits_to_jump(bits: u8) -> &'static str {
+    match bits {
+        0b000 => "null",
+        0b001 => "JGT",
+        0b010 => "JEQ",
+        0b011 => "JGE",
+        0b100 => "JLT",
+        0b101 => "JNE",
+        0b110 => "JLE",
+        0b111 => "JMP",
+        _ => unreachable!("jump field is only ever 3 bits"),
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum AssembleErrorKind {
+    InvalidComp(String),
+    InvalidJump(String),
+    InvalidDest(String),
+    InvalidNumber(String),
+    AValueTooLarge(usize),
+    MalformedLabel(String),
+    MalformedMacro(String),
+    MacroRecursionTooDeep(String),
 }
 
-enum PartiallyAssembledInstruction {
-    Complete(u16),
-    SymbolicAddress(String),
+/// A single malformed-source diagnostic, carrying the 1-based line and
+/// column of the instruction that produced it.
+#[derive(Debug)]
+pub(crate) struct AssembleError {
+    file: String,
+    line: usize,
+    col: usize,
+    kind: AssembleErrorKind,
+}
+
+impl std::fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match &self.kind {
+            AssembleErrorKind::InvalidComp(comp) => format!("{} is an invalid opcode", comp),
+            AssembleErrorKind::InvalidJump(jump) => format!("{} is not a valid jump code", jump),
+            AssembleErrorKind::InvalidDest(dest) => format!("{} is not a valid dest", dest),
+            AssembleErrorKind::InvalidNumber(text) => {
+                format!("{} is not a valid number", text)
+            }
+            AssembleErrorKind::AValueTooLarge(value) => {
+                format!("the value {} is too big to use in an A instruction", value)
+            }
+            AssembleErrorKind::MalformedLabel(label) => format!("{} is not a valid label", label),
+            AssembleErrorKind::MalformedMacro(message) => message.clone(),
+            AssembleErrorKind::MacroRecursionTooDeep(name) => {
+                format!("macro {} recurses too deeply (possible cycle)", name)
+            }
+        };
+        write!(f, "{}:{}:{}: {}", self.file, self.line, self.col, message)
+    }
 }
 
 fn predefined_symbol_table() -> HashMap<String, u16> {
@@ -31,200 +139,464 @@ fn predefined_symbol_table() -> HashMap<String, u16> {
     result
 }
 
-struct Assembler {
-    state: AssemblerState,
-    instruction_buffer: String,
-    symbol_table: HashMap<String, u16>,
-    extra_data_address: u16,
-    output: Vec<PartiallyAssembledInstruction>,
+fn is_c_instruction(word: u16) -> bool {
+    word & 0b1110_0000_0000_0000 == 0b1110_0000_0000_0000
 }
 
-impl Assembler {
-    fn new() -> Self {
-        Self {
-            state: AssemblerState::LookingForInstruction,
-            instruction_buffer: String::new(),
-            symbol_table: predefined_symbol_table(),
-            extra_data_address: 0b10000,
-            output: Vec::new(),
-        }
-    }
+fn jump_bits_of(word: u16) -> u8 {
+    (word & 0b111) as u8
+}
 
-    fn assemble_a_type_instruction(&mut self) {
-        use PartiallyAssembledInstruction::*;
-        let symbol_or_value = &self.instruction_buffer.trim()[1..];
-        if let Ok(value) = symbol_or_value.parse::<usize>() {
-            if value > 0b01111111_11111111 {
-                panic!("The value {} is too big to use in an A instruction.", value);
-            }
-            self.output.push(Complete(value as u16));
-        } else {
-            self.output
-                .push(SymbolicAddress(String::from(symbol_or_value)));
-        }
+/// How many ROM instructions `--gc` removed, for the report printed to stdout.
+struct GcReport {
+    instructions_before: usize,
+    instructions_removed: usize,
+}
+
+/// Everything `assemble` produces: the machine words destined for `.hack`,
+/// and the final symbol table (predefined symbols, labels resolved to ROM
+/// addresses, and variables auto-allocated from `extra_data_address`).
+struct AssembledProgram {
+    words: Vec<u16>,
+    symbol_table: HashMap<String, u16>,
+    gc_report: Option<GcReport>,
+}
+
+/// Reachability pass for `--gc`: walks the instruction stream from ROM
+/// address 0, following fallthrough plus `@LABEL` / `;JUMP` edges, and
+/// drops any instruction no live jump or entry point can reach. Must run
+/// before `finalize` so label addresses can be recomputed against the
+/// pruned stream.
+fn gc(
+    output: &mut Vec<PartiallyAssembledInstruction>,
+    labels: &mut HashMap<String, u16>,
+    symbol_table: &mut HashMap<String, u16>,
+) -> GcReport {
+    let instructions_before = output.len();
+    if instructions_before == 0 {
+        return GcReport {
+            instructions_before,
+            instructions_removed: 0,
+        };
     }
 
-    fn assemble_c_type_instruction(&mut self) {
-        let mut instruction = self.instruction_buffer.trim();
-        let mut dest = 0;
-        if let Some(index) = instruction.find("=") {
-            let dest_name = &instruction[..index];
-            if dest_name.contains("M") {
-                dest |= 0b1;
+    // For each C-instruction, the ROM address currently held in the A
+    // register: whatever the most recently executed A-instruction loaded,
+    // which may be several instructions earlier (`@LABEL / D=A / 0;JMP` is
+    // an entirely ordinary pattern), not just the one immediately before it.
+    let mut target_for: Vec<Option<usize>> = vec![None; instructions_before];
+    let mut last_loaded: Option<usize> = None;
+    for (i, instr) in output.iter().enumerate() {
+        match instr {
+            PartiallyAssembledInstruction::SymbolicAddress(name) => {
+                last_loaded = labels.get(name).map(|&addr| addr as usize);
             }
-            if dest_name.contains("D") {
-                dest |= 0b10;
+            PartiallyAssembledInstruction::Complete(word) if !is_c_instruction(*word) => {
+                last_loaded = labels
+                    .values()
+                    .find(|&&addr| addr == *word)
+                    .map(|&addr| addr as usize);
             }
-            if dest_name.contains("A") {
-                dest |= 0b100;
+            // A C-instruction with `A` in its dest overwrites the register.
+            PartiallyAssembledInstruction::Complete(word) if (word >> 3) & 0b100 != 0 => {
+                last_loaded = None;
             }
-            instruction = &instruction[index + 1..];
-        }
-        let mut jmp = 0;
-        if let Some(index) = instruction.find(";") {
-            let jmp_name = &instruction[index + 1..];
-            jmp = match jmp_name {
-                "null" => 0b000,
-                "JGT" => 0b001,
-                "JEQ" => 0b010,
-                "JGE" => 0b011,
-                "JLT" => 0b100,
-                "JNE" => 0b101,
-                "JLE" => 0b110,
-                "JMP" => 0b111,
-                _ => panic!("{} is not a valid jump code", jmp_name),
-            };
-            instruction = &instruction[..index];
+            _ => {}
         }
-        let comp = match instruction {
-            "0" => 0b0_101010,
-            "1" => 0b0_111111,
-            "-1" => 0b0_111010,
-            "D" => 0b0_001100,
-            "A" => 0b0_110000,
-            "!D" => 0b0_001101,
-            "!A" => 0b0_110001,
-            "-D" => 0b0_001111,
-            "-A" => 0b0_110011,
-            "D+1" => 0b0_011111,
-            "A+1" => 0b0_110111,
-            "D-1" => 0b0_001110,
-            "A-1" => 0b0_110010,
-            "D+A" => 0b0_000010,
-            "D-A" => 0b0_010011,
-            "A-D" => 0b0_000111,
-            "D&A" => 0b0_000000,
-            "D|A" => 0b0_010101,
-
-            "M" => 0b1_110000,
-            "!M" => 0b1_110001,
-            "-M" => 0b1_110011,
-            "M+1" => 0b1_110111,
-            "M-1" => 0b1_110010,
-            "D+M" => 0b1_000010,
-            "D-M" => 0b1_010011,
-            "M-D" => 0b1_000111,
-            "D&M" => 0b1_000000,
-            "D|M" => 0b1_010101,
-
-            _ => panic!("{} is an invalid opcode", instruction),
-        };
-        let full = 0b111_00000_00000000 | comp << 6 | dest << 3 | jmp;
-        self.output
-            .push(PartiallyAssembledInstruction::Complete(full));
-    }
-
-    fn assemble_instruction(&mut self) {
-        let trimmed = self.instruction_buffer.trim();
-        if trimmed.len() > 0 {
-            let first_char = trimmed.chars().next().unwrap();
-            if first_char == '@' {
-                // A-type instruction
-                self.assemble_a_type_instruction()
-            } else if first_char == '(' {
-                // Label metainstruction
-                let symbol_name = &trimmed[1..trimmed.len() - 1];
-                self.symbol_table
-                    .insert(String::from(symbol_name), self.output.len() as _);
-            } else {
-                self.assemble_c_type_instruction();
-            }
+        target_for[i] = last_loaded;
+    }
+    let all_label_addresses: Vec<usize> = labels.values().map(|&addr| addr as usize).collect();
+
+    let mut reachable = vec![false; instructions_before];
+    let mut worklist = vec![0usize];
+    while let Some(i) = worklist.pop() {
+        if i >= instructions_before || reachable[i] {
+            continue;
         }
-        self.instruction_buffer.clear();
-    }
-
-    fn assemble_source(mut self, source: &str) -> Vec<u16> {
-        use AssemblerState::*;
-        for c in source.chars() {
-            match self.state {
-                LookingForInstruction => match c {
-                    '/' => self.state = OneSlash,
-                    '\n' => self.assemble_instruction(),
-                    _ => self.instruction_buffer.push(c),
-                },
-                OneSlash => match c {
-                    '/' => self.state = Comment,
-                    _ => {
-                        self.state = LookingForInstruction;
-                        self.instruction_buffer.push('/');
-                        self.instruction_buffer.push(c);
+        reachable[i] = true;
+        if let PartiallyAssembledInstruction::Complete(word) = &output[i] {
+            if is_c_instruction(*word) {
+                let jump = jump_bits_of(*word);
+                if jump != 0 {
+                    match target_for[i] {
+                        Some(target) => worklist.push(target),
+                        None => {
+                            // Can't prove where this jumps to (e.g. a
+                            // forward-unresolved or computed address):
+                            // conservatively treat every label as reachable
+                            // rather than risk pruning live code.
+                            worklist.extend(all_label_addresses.iter().copied());
+                        }
                     }
-                },
-                Comment => match c {
-                    '\n' => {
-                        self.assemble_instruction();
-                        self.state = LookingForInstruction;
+                    if jump == 0b111 && target_for[i].is_some() {
+                        // Unconditional jump with a known target: never
+                        // falls through.
+                        continue;
                     }
-                    _ => (),
-                },
+                }
             }
         }
-        self.assemble_instruction();
-        self.finalize()
-    }
-
-    fn finalize_instruction(&mut self, instruction: PartiallyAssembledInstruction) -> u16 {
-        use PartiallyAssembledInstruction::*;
-        match instruction {
-            Complete(value) => value,
-            SymbolicAddress(symbol) => {
-                if let Some(value) = self.symbol_table.get(&symbol) {
-                    *value as _
-                } else {
-                    let address = self.extra_data_address;
-                    self.extra_data_address += 1;
-                    self.symbol_table.insert(symbol, address);
-                    address
-                }
+        worklist.push(i + 1);
+    }
+
+    // Map each old ROM address to its post-pruning address: the count of
+    // reachable instructions before it. One extra slot covers a label
+    // declared at the very end of the file, with no instruction after it.
+    let mut new_address = vec![0usize; instructions_before + 1];
+    let mut next = 0;
+    for i in 0..instructions_before {
+        new_address[i] = next;
+        if reachable[i] {
+            next += 1;
+        }
+    }
+    new_address[instructions_before] = next;
+
+    let retained: Vec<PartiallyAssembledInstruction> = std::mem::take(output)
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| reachable[*i])
+        .map(|(_, instr)| instr)
+        .collect();
+    *output = retained;
+
+    for (name, address) in labels.iter_mut() {
+        let remapped = new_address[*address as usize] as u16;
+        *address = remapped;
+        symbol_table.insert(name.clone(), remapped);
+    }
+
+    GcReport {
+        instructions_before,
+        instructions_removed: instructions_before - next,
+    }
+}
+
+fn finalize_instruction(
+    symbol_table: &mut HashMap<String, u16>,
+    extra_data_address: &mut u16,
+    instruction: PartiallyAssembledInstruction,
+) -> u16 {
+    use PartiallyAssembledInstruction::*;
+    match instruction {
+        Complete(value) => value,
+        SymbolicAddress(symbol) => {
+            if let Some(value) = symbol_table.get(&symbol) {
+                *value as _
+            } else {
+                let address = *extra_data_address;
+                *extra_data_address += 1;
+                symbol_table.insert(symbol, address);
+                address
             }
         }
     }
+}
+
+fn finalize(
+    output: Vec<PartiallyAssembledInstruction>,
+    mut symbol_table: HashMap<String, u16>,
+) -> AssembledProgram {
+    let mut extra_data_address: u16 = 0b10000;
+    let words = output
+        .into_iter()
+        .map(|i| finalize_instruction(&mut symbol_table, &mut extra_data_address, i))
+        .collect();
+    AssembledProgram {
+        words,
+        symbol_table,
+        gc_report: None,
+    }
+}
+
+/// Macro-expand, tokenise, parse, and codegen `source` into machine words,
+/// collecting every diagnostic from every stage rather than stopping at the
+/// first. `--gc` (if requested) runs between codegen and `finalize`, since it
+/// needs to recompute label addresses before variables get auto-allocated.
+fn assemble(
+    source: &str,
+    file: &str,
+    gc_enabled: bool,
+) -> Result<AssembledProgram, Vec<AssembleError>> {
+    let (expanded, line_map, mut errors) = MacroExpander::new(file).expand(source);
+    let tokens = tokeniser::tokenise(&expanded, &line_map);
+    let (instructions, parse_errors) = parser::parse(&tokens, file);
+    errors.extend(parse_errors);
+    let (mut output, mut labels, codegen_errors) = codegen::generate(&instructions, file);
+    errors.extend(codegen_errors);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut symbol_table = predefined_symbol_table();
+    for (name, address) in &labels {
+        symbol_table.insert(name.clone(), *address);
+    }
 
-    fn finalize(mut self) -> Vec<u16> {
-        let partial = std::mem::take(&mut self.output);
-        partial
-            .into_iter()
-            .map(|i| self.finalize_instruction(i))
-            .collect()
+    let gc_report = if gc_enabled {
+        Some(gc(&mut output, &mut labels, &mut symbol_table))
+    } else {
+        None
+    };
+    let mut program = finalize(output, symbol_table);
+    program.gc_report = gc_report;
+    Ok(program)
+}
+
+/// Reverses `assemble`: turns a sequence of 16-bit Hack machine words back
+/// into readable assembly, one instruction per line.
+fn disassemble(machine: &[u16]) -> String {
+    let mut result = String::with_capacity(machine.len() * 12);
+    for &word in machine {
+        result.push_str(&disassemble_instruction(word));
+        result.push('\n');
+    }
+    result
+}
+
+fn disassemble_instruction(word: u16) -> String {
+    if word & 0b1000_0000_0000_0000 == 0 {
+        return format!("@{}", word);
+    }
+    let comp_bits = ((word >> 6) & 0b111_1111) as u8;
+    let dest_bits = ((word >> 3) & 0b111) as u8;
+    let jump_bits = (word & 0b111) as u8;
+    let comp = match bits_to_comp(comp_bits) {
+        Some(comp) => comp.to_string(),
+        None => return format!("// undefined comp pattern, raw instruction: {:016b}", word),
+    };
+    let mut line = String::new();
+    if dest_bits != 0 {
+        line.push_str(&bits_to_dest(dest_bits));
+        line.push('=');
     }
+    line.push_str(&comp);
+    if jump_bits != 0 {
+        line.push(';');
+        line.push_str(bits_to_jump(jump_bits));
+    }
+    line
 }
 
-fn assemble(source: &str) -> Vec<u16> {
-    Assembler::new().assemble_source(source)
+/// Parses a `.hack` file's worth of `0`/`1` lines into machine words.
+fn parse_hack(source: &str) -> Vec<u16> {
+    source
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| u16::from_str_radix(line, 2).expect("invalid line in .hack file"))
+        .collect()
+}
+
+/// Renders a `.sym` map file: every symbol listed by name, then again by
+/// address (sorted) so variable allocations are easy to eyeball.
+fn render_symbols_file(symbol_table: &HashMap<String, u16>) -> String {
+    let mut by_name: Vec<(&String, &u16)> = symbol_table.iter().collect();
+    by_name.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut by_address: HashMap<u16, Vec<&String>> = HashMap::new();
+    for (name, address) in symbol_table {
+        by_address.entry(*address).or_default().push(name);
+    }
+    let mut addresses: Vec<&u16> = by_address.keys().collect();
+    addresses.sort();
+
+    let mut contents = String::new();
+    contents.push_str("# Symbols by name\n");
+    for (name, address) in &by_name {
+        contents.push_str(&format!("{} = {}\n", name, address));
+    }
+    contents.push_str("\n# Symbols by address\n");
+    for address in &addresses {
+        let mut names = by_address[address].clone();
+        names.sort();
+        for name in names {
+            contents.push_str(&format!("{} = {}\n", address, name));
+        }
+    }
+    contents
 }
 
 fn main() {
-    let filename = std::env::args()
-        .skip(1)
-        .next()
-        .expect("Must specify a filename.");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--disasm") {
+        let filename = args.get(1).expect("--disasm requires a .hack file.");
+        let source = std::fs::read_to_string(filename).expect("Failed to open file.");
+        let machine = parse_hack(&source);
+        let result = disassemble(&machine);
+        let output_name = filename.replace(".hack", ".asm");
+        std::fs::write(&output_name, result).expect("Failed to write to output file.");
+        println!("Wrote output to {}", output_name);
+        return;
+    }
+
+    let mut filename = None;
+    let mut symbols_path = None;
+    let mut gc = false;
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--symbols" {
+            i += 1;
+            symbols_path = Some(args.get(i).expect("--symbols requires a path").clone());
+        } else if args[i] == "--gc" {
+            gc = true;
+        } else {
+            filename = Some(args[i].clone());
+        }
+        i += 1;
+    }
+    let filename = filename.expect("Must specify a filename.");
+
     let source = std::fs::read_to_string(&filename).expect("Failed to open file.");
-    let instructions = assemble(&source[..]);
-    let mut result = String::with_capacity(instructions.len() * 17);
-    for instruction in instructions {
+    let program = match assemble(&source[..], &filename, gc) {
+        Ok(program) => program,
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}", error);
+            }
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(report) = &program.gc_report {
+        println!(
+            "--gc eliminated {} of {} instructions",
+            report.instructions_removed, report.instructions_before
+        );
+    }
+
+    let mut result = String::with_capacity(program.words.len() * 17);
+    for instruction in &program.words {
         result.push_str(&format!("{:016b}\n", instruction));
     }
     let output_name = filename.replace(".asm", ".hack");
     std::fs::write(&output_name, result).expect("Failed to write to output file.");
     println!("Wrote output to {}", output_name);
+
+    if let Some(symbols_path) = symbols_path {
+        std::fs::write(&symbols_path, render_symbols_file(&program.symbol_table))
+            .expect("Failed to write to symbols file.");
+        println!("Wrote symbols to {}", symbols_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_comp_round_trips_through_its_bit_pattern() {
+        for &mnemonic in ALL_COMPS {
+            let bits = comp_to_bits(mnemonic)
+                .unwrap_or_else(|| panic!("{} missing from comp_to_bits", mnemonic));
+            assert_eq!(
+                bits_to_comp(bits),
+                Some(mnemonic),
+                "{} did not round-trip through bits_to_comp",
+                mnemonic
+            );
+        }
+    }
+
+    fn c_instr(dest: Option<&str>, comp: &str, jump: &str) -> PartiallyAssembledInstruction {
+        let dest_bits = dest.map(|d| dest_to_bits(d).unwrap()).unwrap_or(0);
+        let comp_bits = comp_to_bits(comp).unwrap();
+        let jump_bits = jump_to_bits(jump).unwrap();
+        PartiallyAssembledInstruction::Complete(
+            0b111_00000_00000000
+                | (comp_bits as u16) << 6
+                | (dest_bits as u16) << 3
+                | jump_bits as u16,
+        )
+    }
+
+    fn a_symbol(name: &str) -> PartiallyAssembledInstruction {
+        PartiallyAssembledInstruction::SymbolicAddress(name.to_string())
+    }
+
+    fn a_raw(value: u16) -> PartiallyAssembledInstruction {
+        PartiallyAssembledInstruction::Complete(value)
+    }
+
+    // Regression test for the bug fixed in 412af85: an unconditional jump's
+    // target was loaded several instructions earlier (`@LOOP / D=A / 0;JMP`),
+    // not by the instruction directly before it, and gc() used to only look
+    // one instruction back -- pruning the real target and everything live
+    // after it.
+    #[test]
+    fn gc_resolves_a_jump_target_loaded_several_instructions_earlier() {
+        let mut output = vec![
+            a_symbol("LOOP"),                // 0
+            c_instr(Some("D"), "A", "null"), // 1
+            c_instr(None, "0", "JMP"),       // 2: 0;JMP to LOOP
+            a_raw(999),                      // 3: dead
+            c_instr(Some("D"), "A", "null"), // 4: dead
+            a_raw(1),                        // 5: (LOOP)
+            c_instr(Some("D"), "A", "null"), // 6
+            a_symbol("END"),                 // 7: (END)
+            c_instr(None, "0", "JMP"),       // 8: 0;JMP to END
+        ];
+        let mut labels = HashMap::new();
+        labels.insert("LOOP".to_string(), 5);
+        labels.insert("END".to_string(), 7);
+        let mut symbol_table = HashMap::new();
+
+        let report = gc(&mut output, &mut labels, &mut symbol_table);
+
+        assert_eq!(report.instructions_before, 9);
+        assert_eq!(report.instructions_removed, 2);
+        assert_eq!(output.len(), 7);
+        assert_eq!(labels["LOOP"], 3);
+        assert_eq!(labels["END"], 5);
+    }
+
+    // A jump whose target can't be proven (the address loaded into A isn't
+    // a known label) must not cause gc() to prune anything it isn't sure
+    // about -- every label should stay reachable rather than risk another
+    // silent miscompilation.
+    #[test]
+    fn gc_treats_an_unprovable_jump_target_as_reaching_every_label() {
+        let mut output = vec![
+            a_symbol("x"),                   // 0: not a label, an unresolved variable
+            c_instr(None, "0", "JMP"),       // 1: 0;JMP to an address gc() can't prove
+            a_raw(1),                        // 2: (DEAD), also reached by fall-through from 1
+            c_instr(Some("D"), "A", "null"), // 3
+        ];
+        let mut labels = HashMap::new();
+        labels.insert("DEAD".to_string(), 2);
+        let mut symbol_table = HashMap::new();
+
+        let report = gc(&mut output, &mut labels, &mut symbol_table);
+
+        assert_eq!(report.instructions_removed, 0);
+        assert_eq!(output.len(), 4);
+    }
+
+    // Ordinary dead code after an unconditional jump -- the instructions
+    // past it, referenced only from other dead code, are the ones gc()
+    // exists to strip.
+    #[test]
+    fn gc_strips_dead_code_after_an_unconditional_jump() {
+        let mut output = vec![
+            a_raw(1),                        // 0
+            c_instr(Some("D"), "A", "null"), // 1
+            a_symbol("LOOP"),                // 2: (LOOP)
+            c_instr(None, "0", "JMP"),       // 3: 0;JMP to LOOP
+            a_symbol("DEAD"),                // 4: dead
+            c_instr(Some("D"), "A", "null"), // 5: dead
+            a_raw(0),                        // 6: (DEAD)
+            c_instr(Some("M"), "D", "null"), // 7: dead
+        ];
+        let mut labels = HashMap::new();
+        labels.insert("LOOP".to_string(), 2);
+        labels.insert("DEAD".to_string(), 6);
+        let mut symbol_table = HashMap::new();
+
+        let report = gc(&mut output, &mut labels, &mut symbol_table);
+
+        assert_eq!(report.instructions_before, 8);
+        assert_eq!(report.instructions_removed, 4);
+        assert_eq!(output.len(), 4);
+        assert_eq!(labels["LOOP"], 2);
+    }
 }
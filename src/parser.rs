@@ -0,0 +1,148 @@
+//! Groups the token stream into lines and each line into one AST
+//! `Instruction`. Two-pass label/address resolution happens later in
+//! codegen; the parser's only job is structure.
+
+use crate::ast::{ATarget, Instruction, SpannedInstruction};
+use crate::token::{Token, TokenKind};
+use crate::{AssembleError, AssembleErrorKind};
+
+pub(crate) fn parse(tokens: &[Token], file: &str) -> (Vec<SpannedInstruction>, Vec<AssembleError>) {
+    let mut instructions = Vec::new();
+    let mut errors = Vec::new();
+    let mut line_tokens: Vec<&Token> = Vec::new();
+
+    for token in tokens {
+        if token.kind == TokenKind::Newline {
+            if !line_tokens.is_empty() {
+                match parse_line(&line_tokens, file) {
+                    Ok(instruction) => instructions.push(instruction),
+                    Err(error) => errors.push(error),
+                }
+                line_tokens.clear();
+            }
+        } else {
+            line_tokens.push(token);
+        }
+    }
+
+    (instructions, errors)
+}
+
+fn parse_line(tokens: &[&Token], file: &str) -> Result<SpannedInstruction, AssembleError> {
+    let line = tokens[0].line;
+    let col = tokens[0].col;
+
+    let instruction = match &tokens[0].kind {
+        TokenKind::At => {
+            let target = match tokens.get(1).map(|t| &t.kind) {
+                Some(TokenKind::Number(value)) if tokens.len() == 2 => {
+                    ATarget::Value(value.clone())
+                }
+                Some(TokenKind::Ident(name)) if tokens.len() == 2 => ATarget::Symbol(name.clone()),
+                _ => {
+                    return Err(AssembleError {
+                        file: file.to_string(),
+                        line,
+                        col,
+                        kind: AssembleErrorKind::MalformedLabel(render_tokens(tokens)),
+                    })
+                }
+            };
+            Instruction::A { target }
+        }
+        TokenKind::LabelOpen => {
+            let name = match (
+                tokens.get(1).map(|t| &t.kind),
+                tokens.get(2).map(|t| &t.kind),
+            ) {
+                (Some(TokenKind::Ident(name)), Some(TokenKind::LabelClose))
+                    if tokens.len() == 3 =>
+                {
+                    name.clone()
+                }
+                _ => {
+                    return Err(AssembleError {
+                        file: file.to_string(),
+                        line,
+                        col,
+                        kind: AssembleErrorKind::MalformedLabel(render_tokens(tokens)),
+                    })
+                }
+            };
+            Instruction::Label { name }
+        }
+        _ => return parse_c_instruction(tokens, line, col, file),
+    };
+
+    Ok(SpannedInstruction {
+        instruction,
+        line,
+        col,
+    })
+}
+
+fn parse_c_instruction(
+    tokens: &[&Token],
+    line: usize,
+    col: usize,
+    file: &str,
+) -> Result<SpannedInstruction, AssembleError> {
+    let mut rest = tokens;
+    let mut dest = None;
+    if rest.len() >= 2 && rest[1].kind == TokenKind::Equals {
+        dest = Some(token_text(rest[0]));
+        rest = &rest[2..];
+    }
+
+    let mut jump = None;
+    if let Some(pos) = rest.iter().position(|t| t.kind == TokenKind::Semicolon) {
+        if pos + 1 < rest.len() {
+            jump = Some(token_text(rest[pos + 1]));
+        }
+        rest = &rest[..pos];
+    }
+
+    if rest.len() != 1 {
+        return Err(AssembleError {
+            file: file.to_string(),
+            line,
+            col,
+            kind: AssembleErrorKind::InvalidComp(render_tokens(rest)),
+        });
+    }
+
+    Ok(SpannedInstruction {
+        instruction: Instruction::C {
+            dest,
+            comp: token_text(rest[0]),
+            jump,
+        },
+        line,
+        col,
+    })
+}
+
+fn token_text(token: &Token) -> String {
+    match &token.kind {
+        TokenKind::Ident(s) => s.clone(),
+        TokenKind::Number(n) => n.clone(),
+        _ => String::new(),
+    }
+}
+
+fn render_tokens(tokens: &[&Token]) -> String {
+    tokens.iter().map(|t| render_token(t)).collect()
+}
+
+fn render_token(token: &Token) -> String {
+    match &token.kind {
+        TokenKind::At => "@".to_string(),
+        TokenKind::LabelOpen => "(".to_string(),
+        TokenKind::LabelClose => ")".to_string(),
+        TokenKind::Equals => "=".to_string(),
+        TokenKind::Semicolon => ";".to_string(),
+        TokenKind::Ident(s) => s.clone(),
+        TokenKind::Number(n) => n.clone(),
+        TokenKind::Newline => String::new(),
+    }
+}
@@ -0,0 +1,24 @@
+//! The token stream the tokeniser produces and the parser consumes.
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind {
+    At,
+    LabelOpen,
+    LabelClose,
+    Ident(String),
+    /// The raw digit text, kept unparsed here — an all-digit literal can
+    /// overflow `usize` (e.g. `@99999999999999999999`), and that's a
+    /// diagnostic for codegen to report, not a reason for the tokeniser to
+    /// panic.
+    Number(String),
+    Equals,
+    Semicolon,
+    Newline,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) line: usize,
+    pub(crate) col: usize,
+}
@@ -0,0 +1,142 @@
+//! Turns `.asm` source (with macros already expanded) into a flat token
+//! stream. Comments and insignificant whitespace are stripped here, so the
+//! parser only ever has to deal with meaningful tokens.
+
+use crate::token::{Token, TokenKind};
+
+/// Tokenises macro-expanded `source`. `line_map[i]` is the original source
+/// line that produced flattened line `i + 1` — macro expansion changes the
+/// line count, so tokens are stamped with the mapped line rather than the
+/// flattened one, or diagnostics would point at the wrong place.
+pub(crate) fn tokenise(source: &str, line_map: &[usize]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut flat_line = 1;
+    let mut col = 1;
+    let source_line = |flat_line: usize| line_map.get(flat_line - 1).copied().unwrap_or(flat_line);
+
+    while let Some(&c) = chars.peek() {
+        let line = source_line(flat_line);
+        match c {
+            '\n' => {
+                tokens.push(Token {
+                    kind: TokenKind::Newline,
+                    line,
+                    col,
+                });
+                chars.next();
+                flat_line += 1;
+                col = 1;
+            }
+            ' ' | '\t' | '\r' => {
+                chars.next();
+                col += 1;
+            }
+            '/' if peek_second(&mut chars.clone()) == Some('/') => {
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    chars.next();
+                    col += 1;
+                }
+            }
+            '@' => push_symbol(&mut tokens, &mut chars, TokenKind::At, line, &mut col),
+            '(' => push_symbol(
+                &mut tokens,
+                &mut chars,
+                TokenKind::LabelOpen,
+                line,
+                &mut col,
+            ),
+            ')' => push_symbol(
+                &mut tokens,
+                &mut chars,
+                TokenKind::LabelClose,
+                line,
+                &mut col,
+            ),
+            '=' => push_symbol(&mut tokens, &mut chars, TokenKind::Equals, line, &mut col),
+            ';' => push_symbol(
+                &mut tokens,
+                &mut chars,
+                TokenKind::Semicolon,
+                line,
+                &mut col,
+            ),
+            c if c.is_ascii_digit() => {
+                let start_col = col;
+                let mut text = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_digit() {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                    col += 1;
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Number(text),
+                    line,
+                    col: start_col,
+                });
+            }
+            c if is_ident_char(c) => {
+                let start_col = col;
+                let mut text = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !is_ident_char(c) {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                    col += 1;
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Ident(text),
+                    line,
+                    col: start_col,
+                });
+            }
+            _ => {
+                // An unrecognised character (e.g. a lone `/`): skip it so the
+                // tokeniser always makes forward progress. The character is
+                // dropped; the parser reports its line only if the remaining
+                // tokens no longer form a valid instruction.
+                chars.next();
+                col += 1;
+            }
+        }
+    }
+    tokens.push(Token {
+        kind: TokenKind::Newline,
+        line: source_line(flat_line),
+        col,
+    });
+    tokens
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '!' | '&' | '|' | '_' | '$')
+}
+
+fn push_symbol(
+    tokens: &mut Vec<Token>,
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    kind: TokenKind,
+    line: usize,
+    col: &mut usize,
+) {
+    tokens.push(Token {
+        kind,
+        line,
+        col: *col,
+    });
+    chars.next();
+    *col += 1;
+}
+
+fn peek_second(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<char> {
+    chars.next();
+    chars.next()
+}